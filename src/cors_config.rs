@@ -0,0 +1,195 @@
+//! Configuration-driven CORS policy.
+//!
+//! Previously the router hard-coded `allow_origin(Any).allow_methods([GET])`.
+//! `CorsConfig` loads an origin allowlist, method/header lists, credentials,
+//! and max-age from a TOML file's `[cors]` table (path given by
+//! `CORS_CONFIG_PATH`), then lets a handful of `CORS_*` env vars override
+//! individual fields on top of that. `build_layer` turns the result into a
+//! `tower_http::cors::CorsLayer`.
+//!
+//! Preflight `OPTIONS` requests: `CorsLayer` answers these itself, before the
+//! request ever reaches the router's path matching, so a preflight for a path
+//! that doesn't otherwise exist still gets the correct
+//! `Access-Control-Allow-*` headers — nothing extra to wire up here.
+
+use std::time::Duration;
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use serde::Deserialize;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::warn;
+
+/// The `[cors]` table of a `CORS_CONFIG_PATH` TOML file.
+#[derive(Debug, Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    cors: CorsConfig,
+}
+
+/// The CORS policy to build a `CorsLayer` from.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Allowed origins. Empty means "allow any origin" (via `Any`), which is
+    /// incompatible with `allow_credentials`. A non-empty list is reflected
+    /// against the request's `Origin` instead, which is what lets
+    /// `allow_credentials` be used at all.
+    pub origins: Vec<String>,
+    /// Allowed request methods. Defaults to `["GET"]`, matching the original
+    /// hard-coded policy.
+    pub methods: Vec<String>,
+    /// Request headers the browser is allowed to send.
+    pub allowed_headers: Vec<String>,
+    /// Response headers exposed to the page's JavaScript.
+    pub exposed_headers: Vec<String>,
+    /// Whether to send `Access-Control-Allow-Credentials: true`.
+    pub allow_credentials: bool,
+    /// How long, in seconds, a preflight response may be cached.
+    pub max_age_secs: Option<u64>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            origins: Vec::new(),
+            methods: vec!["GET".to_string()],
+            allowed_headers: Vec::new(),
+            exposed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age_secs: None,
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Loads the `[cors]` table from `CORS_CONFIG_PATH`, if set and readable,
+    /// then applies `CORS_ORIGINS`, `CORS_METHODS`, `CORS_ALLOWED_HEADERS`,
+    /// `CORS_EXPOSED_HEADERS`, `CORS_ALLOW_CREDENTIALS`, and
+    /// `CORS_MAX_AGE_SECS` overrides (each comma-separated where a list is
+    /// expected) on top. Falls back to the hard-coded default on any error, so
+    /// a bad config can't stop the server from starting.
+    pub fn load_from_env() -> CorsConfig {
+        let mut config = std::env::var_os("CORS_CONFIG_PATH")
+            .and_then(|path| match std::fs::read_to_string(&path) {
+                Ok(contents) => match toml::from_str::<ConfigFile>(&contents) {
+                    Ok(file) => Some(file.cors),
+                    Err(e) => {
+                        warn!("Could not parse CORS config {path:?}: {e}. Using defaults.");
+                        None
+                    }
+                },
+                Err(e) => {
+                    warn!("Could not read CORS config {path:?}: {e}. Using defaults.");
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        if let Ok(origins) = std::env::var("CORS_ORIGINS") {
+            config.origins = split_list(&origins);
+        }
+        if let Ok(methods) = std::env::var("CORS_METHODS") {
+            config.methods = split_list(&methods);
+        }
+        if let Ok(headers) = std::env::var("CORS_ALLOWED_HEADERS") {
+            config.allowed_headers = split_list(&headers);
+        }
+        if let Ok(headers) = std::env::var("CORS_EXPOSED_HEADERS") {
+            config.exposed_headers = split_list(&headers);
+        }
+        if let Ok(value) = std::env::var("CORS_ALLOW_CREDENTIALS") {
+            config.allow_credentials = value.eq_ignore_ascii_case("true") || value == "1";
+        }
+        if let Ok(value) = std::env::var("CORS_MAX_AGE_SECS") {
+            match value.parse() {
+                Ok(secs) => config.max_age_secs = Some(secs),
+                Err(e) => warn!("CORS_MAX_AGE_SECS={value:?} is not a number: {e}. Ignoring."),
+            }
+        }
+
+        config
+    }
+
+    /// Builds the `CorsLayer` this config describes.
+    pub fn build_layer(&self) -> CorsLayer {
+        let mut layer = CorsLayer::new();
+
+        layer = if self.origins.is_empty() {
+            layer.allow_origin(Any)
+        } else {
+            let origins: Vec<HeaderValue> = self
+                .origins
+                .iter()
+                .filter_map(|origin| parse_or_warn(origin, "origin"))
+                .collect();
+            layer.allow_origin(AllowOrigin::list(origins))
+        };
+
+        let methods: Vec<Method> = self
+            .methods
+            .iter()
+            .filter_map(|method| parse_or_warn::<Method>(method, "method"))
+            .collect();
+        layer = layer.allow_methods(methods);
+
+        if !self.allowed_headers.is_empty() {
+            let headers: Vec<HeaderName> = self
+                .allowed_headers
+                .iter()
+                .filter_map(|header| parse_or_warn::<HeaderName>(header, "allowed header"))
+                .collect();
+            layer = layer.allow_headers(headers);
+        }
+
+        if !self.exposed_headers.is_empty() {
+            let headers: Vec<HeaderName> = self
+                .exposed_headers
+                .iter()
+                .filter_map(|header| parse_or_warn::<HeaderName>(header, "exposed header"))
+                .collect();
+            layer = layer.expose_headers(headers);
+        }
+
+        if self.allow_credentials && self.origins.is_empty() {
+            // `Any` + `allow_credentials(true)` is an invalid combination that
+            // tower_http only rejects at request time (by panicking while
+            // building the CORS response), not here at construction time.
+            // Refuse to hand it an unsatisfiable config.
+            warn!(
+                "CORS_ALLOW_CREDENTIALS is set but no origin allowlist is configured; \
+                 allow_credentials can't be combined with the `Any` origin policy. \
+                 Ignoring allow_credentials."
+            );
+        } else if self.allow_credentials {
+            layer = layer.allow_credentials(true);
+        }
+
+        if let Some(secs) = self.max_age_secs {
+            layer = layer.max_age(Duration::from_secs(secs));
+        }
+
+        layer
+    }
+}
+
+fn split_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn parse_or_warn<T>(raw: &str, kind: &str) -> Option<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match raw.parse() {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Ignoring invalid CORS {kind} {raw:?}: {e}");
+            None
+        }
+    }
+}
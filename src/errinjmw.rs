@@ -0,0 +1,226 @@
+//! Configurable fault-injection subsystem.
+//!
+//! Replaces the old `gstate::PREBREAK`/`POSTBREAK` all-or-nothing booleans with a
+//! list of rules: each can match on path/method, fire with some probability,
+//! inject an arbitrary status code, and add artificial latency. The config is
+//! loaded once at startup (see `FaultConfig::load_from_env`) and stashed in
+//! `FAULT_CONFIG` for the middleware to read on every request.
+//!
+//! This is a hand-written `tower::Layer`/`Service` pair rather than
+//! `axum::middleware::from_fn`: `from_fn`'s `Service` impl requires its async
+//! fn to return something that implements `IntoResponse` directly, which a
+//! boxed error does not. Being a real fallible `Service<_, Error = BoxError>`
+//! is what lets `HandleErrorLayer` (see `main`) sit in front of it.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::response::Response;
+use axum::BoxError;
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+use tower::{Layer, Service};
+use tracing::{info, warn};
+
+use crate::AppError;
+
+/// Header that forces a specific injected status on this one request, bypassing
+/// rule matching and probability entirely. Meant for targeted testing, e.g.
+/// `curl -H 'x-fault-inject: 500' ...`.
+const FORCE_HEADER: &str = "x-fault-inject";
+
+/// The process-wide fault configuration, set once in `main` before the server
+/// starts accepting connections.
+pub static FAULT_CONFIG: OnceCell<FaultConfig> = OnceCell::const_new();
+
+/// One fault-injection rule.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FaultRule {
+    /// Only matches requests whose path starts with this prefix. `None` matches any path.
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    /// Only matches requests with this method (case-insensitive, e.g. `"GET"`).
+    /// `None` matches any method.
+    #[serde(default)]
+    pub method: Option<String>,
+    /// Chance, in `[0.0, 1.0]`, that a matching request is failed.
+    #[serde(default = "default_probability")]
+    pub probability: f64,
+    /// The status code to inject when this rule fires.
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// Artificial latency to add before responding to a matching request,
+    /// whether or not the rule ends up firing.
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+}
+
+fn default_probability() -> f64 {
+    1.0
+}
+
+fn default_status() -> u16 {
+    StatusCode::INTERNAL_SERVER_ERROR.as_u16()
+}
+
+impl FaultRule {
+    fn matches(&self, request: &Request<Body>) -> bool {
+        if let Some(prefix) = &self.path_prefix {
+            if !request.uri().path().starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+        if let Some(method) = &self.method {
+            if !request.method().as_str().eq_ignore_ascii_case(method) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn latency(&self) -> Option<Duration> {
+        self.latency_ms.map(Duration::from_millis)
+    }
+
+    fn status(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// A list of fault-injection rules, evaluated in order on every request.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FaultConfig {
+    #[serde(rename = "rule", default)]
+    pub rules: Vec<FaultRule>,
+}
+
+impl FaultConfig {
+    /// Load rules from the TOML file named by `FAULT_CONFIG_PATH`.
+    ///
+    /// Falls back to an empty (no-op) config if the variable isn't set, the
+    /// file can't be read, or it doesn't parse — fault injection is opt-in, so
+    /// misconfiguring it should never stop the server from starting.
+    pub fn load_from_env() -> FaultConfig {
+        let Some(path) = std::env::var_os("FAULT_CONFIG_PATH") else {
+            info!("FAULT_CONFIG_PATH not set. Fault injection disabled.");
+            return FaultConfig::default();
+        };
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                warn!("Could not read fault config {path:?}: {e}. Fault injection disabled.");
+                return FaultConfig::default();
+            }
+        };
+        match toml::from_str(&contents) {
+            Ok(config) => {
+                info!("Loaded fault config from {path:?}.");
+                config
+            }
+            Err(e) => {
+                warn!("Could not parse fault config {path:?}: {e}. Fault injection disabled.");
+                FaultConfig::default()
+            }
+        }
+    }
+}
+
+/// Checks the `x-fault-inject` override header first; failing that, walks the
+/// rules in `FAULT_CONFIG` in order. A matching rule sleeps for its configured
+/// latency (if any); then, with its configured probability, it either fires
+/// (returning its configured status and stopping here) or is skipped entirely,
+/// leaving later rules free to match and fire independently of it.
+async fn evaluate(request: &Request<Body>) -> Result<(), AppError> {
+    if let Some(status) = forced_status(request) {
+        return Err(AppError::Fault(
+            status,
+            anyhow::anyhow!("Fault forced via {FORCE_HEADER} header"),
+        ));
+    }
+
+    let config = FAULT_CONFIG.get_or_init(|| async { FaultConfig::default() }).await;
+    for rule in &config.rules {
+        if !rule.matches(request) {
+            continue;
+        }
+        if let Some(latency) = rule.latency() {
+            tokio::time::sleep(latency).await;
+        }
+        if rand::random::<f64>() < rule.probability {
+            return Err(AppError::Fault(
+                rule.status(),
+                anyhow::anyhow!("Injected fault (path_prefix={:?})", rule.path_prefix),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn forced_status(request: &Request<Body>) -> Option<StatusCode> {
+    let value = request.headers().get(FORCE_HEADER)?;
+    let code: u16 = value.to_str().ok()?.parse().ok()?;
+    StatusCode::from_u16(code).ok()
+}
+
+/// `tower::Layer` for the error-injecting middleware.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorInjectionLayer;
+
+impl ErrorInjectionLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for ErrorInjectionLayer {
+    type Service = ErrorInjectionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ErrorInjectionService { inner }
+    }
+}
+
+/// Error-Injecting Middleware.
+///
+/// A genuine fallible `tower::Service`: it returns `Err(BoxError)` (wrapping an
+/// `AppError::Fault`) instead of building a `Response` by hand, so
+/// `HandleErrorLayer::new(handle_error::handle_error)` is the single place
+/// that converts it — and any other fallible middleware sharing this stack —
+/// back into a response.
+#[derive(Debug, Clone)]
+pub struct ErrorInjectionService<S> {
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for ErrorInjectionService<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Error: std::error::Error + Send + Sync + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // Swap in a clone of the inner service so the one we call here is
+        // guaranteed to be the one `poll_ready` was just called on. See
+        // axum's "Writing your own middleware" guide for this pattern.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            evaluate(&request).await?;
+            inner.call(request).await.map_err(Into::into)
+        })
+    }
+}
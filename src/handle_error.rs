@@ -0,0 +1,26 @@
+//! Single place where fallible middleware's boxed errors become `AppError`s.
+//!
+//! Wrapping a fallible layer (like `errinjmw`) with `HandleErrorLayer::new(handle_error)`
+//! means that layer can just return `Result<_, BoxError>` instead of building a
+//! `Response` by hand on every error path. `handle_error` downcasts back to `AppError`
+//! when the inner service raised one (the common case), so the usual
+//! `AppError::into_response` still runs and the problem+json / CORS behavior is
+//! unchanged either way.
+
+use axum::http::{Method, Uri};
+use axum::BoxError;
+use tracing::warn;
+
+use crate::AppError;
+
+/// Converts a boxed error from a fallible inner service into an `AppError`,
+/// with the request's method and path available to enrich logging.
+pub async fn handle_error(method: Method, uri: Uri, err: BoxError) -> AppError {
+    match err.downcast::<AppError>() {
+        Ok(app_error) => *app_error,
+        Err(err) => {
+            warn!("{method} {uri}: unhandled error from inner service: {err}");
+            AppError::InternalServerError(anyhow::anyhow!(err.to_string()))
+        }
+    }
+}
@@ -1,14 +1,25 @@
 //! Test whether the CORS (Access-Control-Allow-Origin) header is set correctly
 //! even when the response fails.
 
-use axum::body::Body;
+use axum::error_handling::HandleErrorLayer;
 use axum::extract::Path;
-use axum::http::{Method, Request, StatusCode};
-use axum::middleware::{self, Next};
+use axum::http::StatusCode;
+use axum::middleware;
 use axum::response::{IntoResponse, Response};
-use axum::routing::get;
+use axum::routing::{any, get};
 use thiserror::Error;
-use tracing::{error, info, warn};
+use tower::ServiceBuilder;
+use tracing::{error, info};
+
+mod cors_config;
+mod errinjmw;
+mod handle_error;
+mod metrics;
+mod negotiation;
+mod problem;
+mod proxy;
+
+use negotiation::ProblemDetail;
 
 /// An error type that implements axum::IntoResponse.
 /// This allows any HTTP errors to be directly thrown by the handler.
@@ -22,54 +33,53 @@ enum AppError {
         #[source]
         anyhow::Error,
     ),
+    /// An arbitrary status code injected by `errinjmw`.
+    #[error("Injected Fault ({0})")]
+    Fault(StatusCode, #[source] anyhow::Error),
 }
 
 // Allows being returned by a handler.
 //
 // It also reveals the error to the client.
+//
+// Still builds the same plain-text body as before. It additionally stashes the
+// `ProblemSource` (fixed type/title/status) and the `anyhow` detail message as
+// response extensions, so `negotiation::negotiate_problem_json` can swap in an
+// `application/problem+json` body for clients that ask for one, without this
+// impl needing to see the request's `Accept` header itself.
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        match self {
-            AppError::NotFound(e) => {
-                (StatusCode::NOT_FOUND, format!("Not Found: {e}")).into_response()
-            }
-            AppError::InternalServerError(e) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, format!("ISE: {e}")).into_response()
-            }
-        }
+        let (status, text, source, detail) = match self {
+            AppError::NotFound(e) => (
+                StatusCode::NOT_FOUND,
+                format!("Not Found: {e}"),
+                problem::NOT_FOUND,
+                e.to_string(),
+            ),
+            AppError::InternalServerError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("ISE: {e}"),
+                problem::INTERNAL_SERVER_ERROR,
+                e.to_string(),
+            ),
+            AppError::Fault(status, e) => (
+                status,
+                format!("Fault: {e}"),
+                problem::ProblemSource {
+                    type_: "about:blank",
+                    title: status.canonical_reason().unwrap_or("Injected Fault"),
+                    status,
+                },
+                e.to_string(),
+            ),
+        };
+        let mut response = (status, text).into_response();
+        response.extensions_mut().insert(source);
+        response.extensions_mut().insert(ProblemDetail(detail));
+        response
     }
 }
 
-mod gstate {
-    //! Quick-and-dirty global state.
-
-    use tokio::sync::OnceCell;
-
-    /// Should the pre-middleware break?
-    pub static PREBREAK: OnceCell<bool> = OnceCell::const_new();
-
-    /// Should the post-middleware break?
-    pub static POSTBREAK: OnceCell<bool> = OnceCell::const_new();
-}
-
-/// Error-Injecting Middleware.
-///
-/// Configure the PREBREAK and POSTBREAK global variables to break the middleware.
-async fn errinjmw(request: Request<Body>, next: Next<Body>) -> Result<impl IntoResponse, AppError> {
-    if *gstate::PREBREAK.get_or_init(|| async move { false }).await {
-        return Err(AppError::InternalServerError(anyhow::anyhow!(
-            "Pre-Middleware Break"
-        )));
-    }
-    let response = next.run(request).await;
-    if *gstate::POSTBREAK.get_or_init(|| async move { false }).await {
-        return Err(AppError::InternalServerError(anyhow::anyhow!(
-            "Post-Middleware Break"
-        )));
-    }
-    Ok(response)
-}
-
 /// A handler that returns "200 OK" when the path is "200,"
 /// "500 Internal Server Error" when the path is "500,"
 /// and "404 Not Found" for any other path or exceptional case.
@@ -130,29 +140,35 @@ async fn main() -> Result<(), &'static str> {
         }
     };
 
-    // For both middlewares, check for the respective environment variable.
-    let prebreak = std::env::var_os("PREBREAK");
-    if prebreak.is_some() {
-        info!("Pre-Middleware Break is set: it will fail.");
-        gstate::PREBREAK.set(true).unwrap();
-    } else {
-        info!("Pre-Middleware Break is not set. Set it using PREBREAK env var.");
-    }
-    let postbreak = std::env::var_os("POSTBREAK");
-    if postbreak.is_some() {
-        info!("Post-Middleware Break is set: it will fail.");
-        gstate::POSTBREAK.set(true).unwrap();
-    } else {
-        info!("Post-Middleware Break is not set. Set it using POSTBREAK env var.");
-    }
-    if prebreak.is_some() && postbreak.is_some() {
-        warn!("Both PREBREAK and POSTBREAK are set. This is weird.");
+    // Load the fault-injection config (see FAULT_CONFIG_PATH) once, before the
+    // server starts accepting connections.
+    errinjmw::FAULT_CONFIG
+        .set(errinjmw::FaultConfig::load_from_env())
+        .expect("FAULT_CONFIG only set once, here");
+
+    // Optional reverse-proxy mode: argv[2], falling back to PROXY_UPSTREAM_URL.
+    let upstream = std::env::args()
+        .nth(2)
+        .and_then(|raw| proxy::parse_upstream(&raw))
+        .or_else(proxy::load_upstream_from_env);
+    match upstream {
+        Some(upstream) => {
+            info!("Proxy mode enabled: forwarding /proxy/* to {upstream}");
+            proxy::UPSTREAM_BASE
+                .set(upstream)
+                .expect("UPSTREAM_BASE only set once, here");
+        }
+        None => {
+            info!(
+                "Proxy mode disabled. Set PROXY_UPSTREAM_URL (or pass as the 2nd \
+                 argument) to enable forwarding /proxy/* to an upstream."
+            );
+        }
     }
 
-    // CORS Layer. Allow listening on any origin, GET requests.
-    let cors = tower_http::cors::CorsLayer::new()
-        .allow_origin(tower_http::cors::Any)
-        .allow_methods([Method::GET]);
+    // CORS Layer, built from CORS_CONFIG_PATH / CORS_* env overrides (defaults
+    // to the original hard-coded policy: any origin, GET only).
+    let cors = cors_config::CorsConfig::load_from_env().build_layer();
 
     // Create a new router.
     let app = axum::Router::new()
@@ -160,13 +176,33 @@ async fn main() -> Result<(), &'static str> {
         // Actually, match everything.
         .route("/:code", get(handler))
         .route("/", get(handler))
+        // A static route, so it wins over the "/:code" catch-all and is served by
+        // `serve_metrics` rather than `handler`. It still passes through the
+        // layers below (fault injection, metrics) like every other route.
+        .route("/metrics", get(metrics::serve_metrics))
+        // Forwards to the configured upstream, when proxy mode is enabled.
+        .route("/proxy/*rest", any(proxy::proxy_handler))
         // Note: fallback is not used to enable testing unhandled routing.
-        // Inject errors at the middleware level.
-        .layer(middleware::from_fn(errinjmw))
+        // Inject errors at the middleware level. `errinjmw` returns a boxed
+        // error; `HandleErrorLayer` is the single place that turns it (and any
+        // other fallible middleware we add later) back into an `AppError`
+        // response, with the request's method/path available to enrich it.
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(handle_error::handle_error))
+                .layer(errinjmw::ErrorInjectionLayer::new()),
+        )
+        // Rewrite error bodies to `application/problem+json` for clients that ask for
+        // it via `Accept`. Placed inside the CORS layer so its output still gets
+        // `Access-Control-Allow-Origin`.
+        .layer(middleware::from_fn(negotiation::negotiate_problem_json))
         // Add a CORS middleware.
         .layer(cors)
         // Add tracing logging.
-        .layer(tower_http::trace::TraceLayer::new_for_http());
+        .layer(tower_http::trace::TraceLayer::new_for_http())
+        // Record metrics last (outermost), so it sees the fully-processed
+        // response, CORS header included.
+        .layer(middleware::from_fn(metrics::record_metrics));
 
     // Let's go
     let str_bind_to = format!("0.0.0.0:{port}");
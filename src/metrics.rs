@@ -0,0 +1,89 @@
+//! Prometheus metrics: request counts by route/status, and whether CORS
+//! headers made it onto the response.
+//!
+//! `record_metrics` must be layered outside `CorsLayer` (further from the
+//! handler) so the response it inspects already carries, or is missing,
+//! `Access-Control-Allow-Origin` — that's the whole point of
+//! `cors_header_present_total`: compare it against `http_requests_total` to
+//! find responses that should have had a CORS header and didn't.
+
+use axum::body::Body;
+use axum::extract::MatchedPath;
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use once_cell::sync::Lazy;
+use prometheus::{Encoder, IntCounterVec, Opts, Registry, TextEncoder};
+use tracing::error;
+
+static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+static HTTP_REQUESTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    let counter = IntCounterVec::new(
+        Opts::new(
+            "http_requests_total",
+            "Total HTTP requests, by matched route and response status",
+        ),
+        &["route", "status"],
+    )
+    .expect("static metric name/labels are valid");
+    REGISTRY
+        .register(Box::new(counter.clone()))
+        .expect("registered exactly once, here");
+    counter
+});
+
+static CORS_HEADER_PRESENT_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    let counter = IntCounterVec::new(
+        Opts::new(
+            "cors_header_present_total",
+            "Responses that carried Access-Control-Allow-Origin, by status",
+        ),
+        &["status"],
+    )
+    .expect("static metric name/labels are valid");
+    REGISTRY
+        .register(Box::new(counter.clone()))
+        .expect("registered exactly once, here");
+    counter
+});
+
+/// Records `http_requests_total` and `cors_header_present_total` for every request.
+pub async fn record_metrics(request: Request<Body>, next: Next<Body>) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched| matched.as_str().to_string())
+        .unwrap_or_else(|| "<unmatched>".to_string());
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    HTTP_REQUESTS_TOTAL
+        .with_label_values(&[route.as_str(), status.as_str()])
+        .inc();
+    if response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN) {
+        CORS_HEADER_PRESENT_TOTAL
+            .with_label_values(&[status.as_str()])
+            .inc();
+    }
+
+    response
+}
+
+/// `GET /metrics`: renders the registry in Prometheus text exposition format.
+pub async fn serve_metrics() -> Response {
+    let encoder = TextEncoder::new();
+    let metric_families = REGISTRY.gather();
+    let mut buffer = Vec::new();
+    if let Err(e) = encoder.encode(&metric_families, &mut buffer) {
+        error!("Failed to encode metrics: {e}");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, encoder.format_type())],
+        buffer,
+    )
+        .into_response()
+}
@@ -0,0 +1,76 @@
+//! Content negotiation for error bodies.
+//!
+//! `AppError::into_response` always builds the plain-text body it has always built,
+//! but it also stashes a [`ProblemSource`] and the `anyhow` detail message as response
+//! extensions. This middleware looks at the incoming `Accept` header and, if the
+//! client wants JSON, swaps the response body for an `application/problem+json` one
+//! built from those extensions. Plain-text clients see no change at all.
+//!
+//! This runs *inside* the `CorsLayer` (i.e. it's added before `.layer(cors)`), so the
+//! `Access-Control-Allow-Origin` header that `CorsLayer` adds on the way out still
+//! lands on the rewritten JSON body.
+
+use axum::body::Body;
+use axum::http::{HeaderMap, HeaderValue, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::problem::ProblemSource;
+
+/// Per-request detail stashed on the response by `AppError::into_response`.
+pub struct ProblemDetail(pub String);
+
+/// Does this `Accept` header indicate the client wants a JSON error body?
+///
+/// This is a quick-and-dirty substring check, not full RFC 7231 media-range
+/// parsing: good enough to flip between "plain text" and "problem+json" for a
+/// demo app.
+fn wants_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(axum::http::header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .map(|accept| accept.contains("json"))
+        .unwrap_or(false)
+}
+
+/// Rewrites error responses into `application/problem+json` when the client asked for it.
+pub async fn negotiate_problem_json(request: Request<Body>, next: Next<Body>) -> Response {
+    let wants_json = wants_json(request.headers());
+    let instance = request.uri().path().to_string();
+
+    let response = next.run(request).await;
+    if !wants_json {
+        return response;
+    }
+
+    let Some(source) = response.extensions().get::<ProblemSource>().copied() else {
+        return response;
+    };
+    let detail = response
+        .extensions()
+        .get::<ProblemDetail>()
+        .map(|d| d.0.clone())
+        .unwrap_or_default();
+
+    let problem = source.with_detail(detail, instance);
+    let mut problem_response = axum::Json(problem).into_response();
+    *problem_response.status_mut() = response.status();
+    problem_response.headers_mut().insert(
+        axum::http::header::CONTENT_TYPE,
+        HeaderValue::from_static("application/problem+json"),
+    );
+    // Carry over any headers the inner service/middleware already attached,
+    // minus what we just overwrote above.
+    //
+    // `iter()`, not `drain()`: `HeaderMap::drain` only reports `Some(name)` on
+    // the first occurrence of a repeated header name, so using it here would
+    // silently drop every value after the first for multi-valued headers like
+    // `Vary` or `Set-Cookie`. `append` (not `insert`) preserves repeats too.
+    for (name, value) in response.headers().iter() {
+        if name == axum::http::header::CONTENT_TYPE {
+            continue;
+        }
+        problem_response.headers_mut().append(name, value.clone());
+    }
+    problem_response
+}
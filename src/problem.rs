@@ -0,0 +1,57 @@
+//! RFC 7807 `application/problem+json` error bodies.
+//!
+//! The handler's errors normally render as plain text (see `AppError::into_response`).
+//! When a client's `Accept` header asks for JSON, [`negotiation`](crate::negotiation)
+//! rewrites the response into one of these instead, reusing the details stashed
+//! on the response by `AppError::into_response`.
+
+use axum::http::StatusCode;
+use serde::Serialize;
+
+/// An RFC 7807 problem detail object.
+#[derive(Debug, Serialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub type_: &'static str,
+    pub title: &'static str,
+    pub status: u16,
+    pub detail: String,
+    pub instance: String,
+}
+
+/// The fixed `type`/`title`/`status` triple for one `AppError` variant.
+///
+/// `detail` and `instance` are filled in per-request, so they aren't part of this.
+#[derive(Debug, Clone, Copy)]
+pub struct ProblemSource {
+    pub type_: &'static str,
+    pub title: &'static str,
+    pub status: StatusCode,
+}
+
+impl ProblemSource {
+    /// Finish the problem details with the per-request `detail` and `instance`.
+    pub fn with_detail(self, detail: String, instance: String) -> ProblemDetails {
+        ProblemDetails {
+            type_: self.type_,
+            title: self.title,
+            status: self.status.as_u16(),
+            detail,
+            instance,
+        }
+    }
+}
+
+/// `type`/`title`/`status` for `AppError::NotFound`.
+pub const NOT_FOUND: ProblemSource = ProblemSource {
+    type_: "https://httpstatuses.io/404",
+    title: "Not Found",
+    status: StatusCode::NOT_FOUND,
+};
+
+/// `type`/`title`/`status` for `AppError::InternalServerError`.
+pub const INTERNAL_SERVER_ERROR: ProblemSource = ProblemSource {
+    type_: "https://httpstatuses.io/500",
+    title: "Internal Server Error",
+    status: StatusCode::INTERNAL_SERVER_ERROR,
+};
@@ -0,0 +1,105 @@
+//! Optional reverse-proxy mode.
+//!
+//! When `UPSTREAM_BASE` is set (see `load_upstream_from_env` and the `main`
+//! startup code), `/proxy/*rest` is forwarded to that upstream and its
+//! response — status, headers, and streamed body — is handed straight back to
+//! the router. It then passes through `errinjmw`/`CorsLayer` exactly like a
+//! response from `handler` would, which is the point: even a backend that
+//! emits no CORS headers of its own gets `Access-Control-Allow-Origin` added
+//! by this app.
+
+use axum::body::Body;
+use axum::http::uri::PathAndQuery;
+use axum::http::{header::HOST, HeaderValue, Request, Uri};
+use axum::response::Response;
+use hyper::client::HttpConnector;
+use hyper::Client;
+use once_cell::sync::{Lazy, OnceCell};
+use tracing::{info, warn};
+
+use crate::AppError;
+
+/// The upstream base URI that `/proxy/*rest` requests are forwarded to. Unset
+/// means proxy mode is disabled.
+pub static UPSTREAM_BASE: OnceCell<Uri> = OnceCell::new();
+
+static CLIENT: Lazy<Client<HttpConnector, Body>> = Lazy::new(Client::new);
+
+/// Reads `PROXY_UPSTREAM_URL`, if set, and parses it as the upstream base URI.
+pub fn load_upstream_from_env() -> Option<Uri> {
+    let raw = std::env::var("PROXY_UPSTREAM_URL").ok()?;
+    parse_upstream(&raw)
+}
+
+/// Parses a CLI-argument or env-var value as an upstream base URI, warning
+/// (rather than failing startup) if it's invalid.
+///
+/// Only `http://` upstreams are supported: `CLIENT` connects with a plain
+/// `HttpConnector`, which has no TLS support, so an `https://` upstream would
+/// otherwise fail opaquely on every single proxied request instead of at
+/// startup.
+pub fn parse_upstream(raw: &str) -> Option<Uri> {
+    let uri = match raw.parse::<Uri>() {
+        Ok(uri) => uri,
+        Err(e) => {
+            warn!("{raw:?} is not a valid upstream URI: {e}. Proxy mode disabled.");
+            return None;
+        }
+    };
+    match uri.scheme_str() {
+        Some("http") => Some(uri),
+        other => {
+            warn!(
+                "Upstream {raw:?} has scheme {other:?}, but only \"http\" is supported \
+                 (no TLS connector is wired in). Proxy mode disabled."
+            );
+            None
+        }
+    }
+}
+
+/// `/proxy/*rest` handler: rewrites the request onto the upstream and streams
+/// its response back unbuffered.
+pub async fn proxy_handler(mut request: Request<Body>) -> Result<Response, AppError> {
+    let upstream = UPSTREAM_BASE
+        .get()
+        .ok_or_else(|| AppError::NotFound(anyhow::anyhow!("Proxy mode is not configured")))?;
+
+    *request.uri_mut() = rewrite_uri(upstream, request.uri())?;
+
+    // hyper uses an existing `Host` header verbatim instead of deriving it
+    // from the URI authority, so without this the upstream would see this
+    // server's own hostname rather than its own.
+    if let Some(authority) = upstream.authority() {
+        let host = HeaderValue::from_str(authority.as_str()).map_err(|e| {
+            AppError::InternalServerError(anyhow::anyhow!("Bad upstream authority: {e}"))
+        })?;
+        request.headers_mut().insert(HOST, host);
+    }
+
+    info!("Proxying {} {}", request.method(), request.uri());
+    let response = CLIENT.request(request).await.map_err(|e| {
+        AppError::InternalServerError(anyhow::anyhow!("Upstream request failed: {e}"))
+    })?;
+
+    // Stream the upstream body straight through instead of buffering it.
+    Ok(response.map(axum::body::boxed))
+}
+
+/// Rewrites `/proxy/<rest>[?query]` onto `<upstream><rest>[?query]`.
+fn rewrite_uri(upstream: &Uri, incoming: &Uri) -> Result<Uri, AppError> {
+    let rest = incoming.path().strip_prefix("/proxy").unwrap_or("");
+    let rest = if rest.is_empty() { "/" } else { rest };
+    let base = upstream.path().trim_end_matches('/');
+    let path_and_query = match incoming.query() {
+        Some(query) => format!("{base}{rest}?{query}"),
+        None => format!("{base}{rest}"),
+    };
+
+    let mut parts = upstream.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse::<PathAndQuery>().map_err(|e| {
+        AppError::InternalServerError(anyhow::anyhow!("Bad upstream path {path_and_query:?}: {e}"))
+    })?);
+    Uri::from_parts(parts)
+        .map_err(|e| AppError::InternalServerError(anyhow::anyhow!("Bad upstream URI: {e}")))
+}